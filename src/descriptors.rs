@@ -0,0 +1,188 @@
+// --- lsusb 风格的描述符树 dump 模式 ---
+// 复用既有的 GET_DESCRIPTOR 读取方式 (标准控制传输 + wTotalLength 两阶段读取)
+// 和字符串描述符解析逻辑，在 Device/Configuration/Interface/Endpoint 粒度上
+// 解码标准字段，打印一棵缩进的描述符树，和 BOS/Billboard 小节并列展示，
+// 使这个工具不再局限于硬编码的 VID/PID。
+
+use std::io;
+use std::time::Duration;
+
+use nusb::transfer::{ControlIn, ControlType, Recipient};
+use nusb::MaybeFuture;
+
+use crate::{get_string_descriptor, REQ_GET_DESCRIPTOR};
+
+const DESC_TYPE_DEVICE: u16 = 0x01;
+const DESC_TYPE_CONFIGURATION: u16 = 0x02;
+const CONTROL_TIMEOUT: Duration = Duration::from_millis(200);
+
+fn transfer_type_name(bm_attributes: u8) -> &'static str {
+    match bm_attributes & 0x03 {
+        0 => "Control",
+        1 => "Isochronous",
+        2 => "Bulk",
+        3 => "Interrupt",
+        _ => unreachable!("2-bit 字段只有 4 种取值"),
+    }
+}
+
+// 读取设备描述符 (18 字节) 的原始数据
+async fn get_device_descriptor(interface: &nusb::Interface) -> io::Result<Vec<u8>> {
+    let req = ControlIn {
+        control_type: ControlType::Standard,
+        recipient: Recipient::Device,
+        request: REQ_GET_DESCRIPTOR,
+        value: DESC_TYPE_DEVICE << 8,
+        index: 0,
+        length: 18,
+    };
+    let data = interface.control_in(req, CONTROL_TIMEOUT).wait()?;
+    Ok(data)
+}
+
+// 先读取 9 字节头部获取 wTotalLength，再读取完整的配置描述符集合
+// (Configuration 描述符本身，加上其下所有 Interface/Endpoint 描述符)
+async fn get_configuration_descriptor(interface: &nusb::Interface, index: u8) -> io::Result<Vec<u8>> {
+    let header_req = ControlIn {
+        control_type: ControlType::Standard,
+        recipient: Recipient::Device,
+        request: REQ_GET_DESCRIPTOR,
+        value: (DESC_TYPE_CONFIGURATION << 8) | (index as u16),
+        index: 0,
+        length: 9,
+    };
+    let header = interface.control_in(header_req, CONTROL_TIMEOUT).wait()?;
+    if header.len() < 9 {
+        return Err(io::Error::new(io::ErrorKind::InvalidData, "配置描述符头太短"));
+    }
+    let total_len = u16::from_le_bytes([header[2], header[3]]);
+
+    let full_req = ControlIn {
+        control_type: ControlType::Standard,
+        recipient: Recipient::Device,
+        request: REQ_GET_DESCRIPTOR,
+        value: (DESC_TYPE_CONFIGURATION << 8) | (index as u16),
+        index: 0,
+        length: total_len,
+    };
+    let data = interface.control_in(full_req, CONTROL_TIMEOUT).wait()?;
+    Ok(data)
+}
+
+async fn print_string_field(interface: &nusb::Interface, label: &str, index: u8) {
+    if index == 0 {
+        return;
+    }
+    match get_string_descriptor(interface, index).await {
+        Ok(s) => println!("{} (#{}): {}", label, index, s),
+        Err(_) => println!("{} (#{}): [读取失败]", label, index),
+    }
+}
+
+// 打印一棵 lsusb 风格的缩进描述符树：Device -> Configuration -> Interface -> Endpoint
+pub async fn dump_descriptor_tree(interface: &nusb::Interface) {
+    println!("\n=== 描述符树 ===");
+
+    let device_desc = match get_device_descriptor(interface).await {
+        Ok(d) => d,
+        Err(e) => {
+            eprintln!("读取设备描述符失败: {}", e);
+            return;
+        }
+    };
+    if device_desc.len() < 18 {
+        eprintln!("设备描述符长度不足");
+        return;
+    }
+
+    let vid = u16::from_le_bytes([device_desc[8], device_desc[9]]);
+    let pid = u16::from_le_bytes([device_desc[10], device_desc[11]]);
+    let b_num_configurations = device_desc[17];
+    let i_manufacturer = device_desc[14];
+    let i_product = device_desc[15];
+    let i_serial = device_desc[16];
+
+    println!("Device: VID=0x{:04X} PID=0x{:04X}", vid, pid);
+    println!("  bNumConfigurations: {}", b_num_configurations);
+    print_string_field(interface, "  iManufacturer", i_manufacturer).await;
+    print_string_field(interface, "  iProduct", i_product).await;
+    print_string_field(interface, "  iSerialNumber", i_serial).await;
+
+    for config_index in 0..b_num_configurations {
+        match get_configuration_descriptor(interface, config_index).await {
+            Ok(data) => dump_configuration(interface, &data).await,
+            Err(e) => eprintln!("  读取配置描述符 #{} 失败: {}", config_index, e),
+        }
+    }
+}
+
+// 解析配置描述符集合里串联的 Configuration/Interface/Endpoint 三类子描述符
+async fn dump_configuration(interface: &nusb::Interface, data: &[u8]) {
+    let mut offset = 0;
+    while offset + 2 <= data.len() {
+        let b_length = data[offset] as usize;
+        let b_descriptor_type = data[offset + 1];
+        if b_length == 0 || offset + b_length > data.len() {
+            break;
+        }
+        let body = &data[offset..offset + b_length];
+
+        match b_descriptor_type {
+            0x02 if body.len() >= 9 => {
+                let b_num_interfaces = body[4];
+                let i_configuration = body[6];
+                println!("  Configuration: bNumInterfaces={}", b_num_interfaces);
+                print_string_field(interface, "    iConfiguration", i_configuration).await;
+            }
+            0x04 if body.len() >= 9 => {
+                let b_interface_number = body[2];
+                let b_interface_class = body[5];
+                let b_interface_sub_class = body[6];
+                let b_interface_protocol = body[7];
+                let i_interface = body[8];
+                println!(
+                    "    Interface #{}: Class=0x{:02X} SubClass=0x{:02X} Protocol=0x{:02X}",
+                    b_interface_number, b_interface_class, b_interface_sub_class, b_interface_protocol
+                );
+                print_string_field(interface, "      iInterface", i_interface).await;
+            }
+            0x05 if body.len() >= 7 => {
+                let b_endpoint_address = body[2];
+                let bm_attributes = body[3];
+                let w_max_packet_size = u16::from_le_bytes([body[4], body[5]]);
+                let b_interval = body[6];
+                println!(
+                    "      Endpoint 0x{:02X}: {} wMaxPacketSize={} bInterval={}",
+                    b_endpoint_address,
+                    transfer_type_name(bm_attributes),
+                    w_max_packet_size,
+                    b_interval
+                );
+            }
+            _ => {}
+        }
+
+        offset += b_length;
+    }
+}
+
+// `list` 子命令：枚举所有已连接设备，打印 VID/PID 和产品/厂商字符串，
+// 方便用户在不知道目标设备 VID/PID 时先定位它
+pub fn list_all_devices() -> io::Result<()> {
+    let devices = nusb::list_devices().wait()?;
+    let mut count = 0;
+    for info in devices {
+        count += 1;
+        println!(
+            "VID:0x{:04X} PID:0x{:04X}  厂商={}  产品={}",
+            info.vendor_id(),
+            info.product_id(),
+            info.manufacturer_string().unwrap_or("未知"),
+            info.product_string().unwrap_or("未知"),
+        );
+    }
+    if count == 0 {
+        println!("未发现任何 USB 设备。");
+    }
+    Ok(())
+}
@@ -0,0 +1,151 @@
+// --- 固件升级子系统 ---
+// 驱动下位机 Bootloader 的 OUT 请求，实现类 DFU 的升级流程：
+//   REQ_SET_FW_INFO_1/2 (固件大小+CRC) -> REQ_SET_ERASE_FLASH -> 分块 REQ_SET_UPDATE_DATA -> REQ_SET_FW_TO_BLDR
+
+use std::io;
+use std::time::Duration;
+
+use nusb::transfer::{ControlOut, ControlType, Recipient};
+use nusb::MaybeFuture;
+
+use crate::{
+    REQ_SET_ERASE_FLASH, REQ_SET_FW_INFO_1, REQ_SET_FW_INFO_2, REQ_SET_FW_TO_BLDR,
+    REQ_SET_UPDATE_DATA,
+};
+
+// 每个 REQ_SET_UPDATE_DATA 控制传输携带的数据块大小
+const FLASH_CHUNK_SIZE: usize = 64;
+// 单个控制传输的超时时间
+const CONTROL_TIMEOUT: Duration = Duration::from_millis(200);
+// 擦除整片 Flash 通常比普通控制传输耗时更久
+const ERASE_TIMEOUT: Duration = Duration::from_secs(5);
+// 每个请求在放弃前最多尝试的次数（含首次发送）
+const MAX_BLOCK_RETRIES: u32 = 3;
+
+// CRC-32 (IEEE 802.3)，用于固件镜像的完整性校验
+fn crc32(data: &[u8]) -> u32 {
+    let mut crc = 0xFFFF_FFFFu32;
+    for &byte in data {
+        crc ^= byte as u32;
+        for _ in 0..8 {
+            if crc & 1 != 0 {
+                crc = (crc >> 1) ^ 0xEDB8_8320;
+            } else {
+                crc >>= 1;
+            }
+        }
+    }
+    !crc
+}
+
+async fn control_out_with_retry(
+    interface: &nusb::Interface,
+    request: u8,
+    value: u16,
+    index: u16,
+    data: &[u8],
+) -> io::Result<()> {
+    let mut last_err = None;
+    for attempt in 1..=MAX_BLOCK_RETRIES {
+        let req = ControlOut {
+            control_type: ControlType::Vendor,
+            recipient: Recipient::Interface,
+            request,
+            value,
+            index,
+            data,
+        };
+        match interface.control_out(req, CONTROL_TIMEOUT).wait() {
+            Ok(_) => return Ok(()),
+            Err(e) => {
+                let retry_note = if attempt < MAX_BLOCK_RETRIES { ", 正在重试..." } else { "" };
+                eprintln!(
+                    "  警告: 请求 0x{:02X} 第 {} 次尝试失败 ({}){}",
+                    request, attempt, e, retry_note
+                );
+                last_err = Some(e);
+            }
+        }
+    }
+    Err(io::Error::new(
+        io::ErrorKind::TimedOut,
+        format!(
+            "请求 0x{:02X} 尝试 {} 次后仍然失败: {}",
+            request,
+            MAX_BLOCK_RETRIES,
+            last_err.unwrap()
+        ),
+    ))
+}
+
+// 驱动完整的固件升级流程：校验信息 -> 擦除 -> 分块写入 -> 跳转 Bootloader
+pub async fn flash_firmware(interface: &nusb::Interface, image_path: &str) -> io::Result<()> {
+    let image = std::fs::read(image_path)?;
+    let size = image.len() as u32;
+    let crc = crc32(&image);
+
+    println!(
+        "固件镜像: {} ({} 字节, CRC32=0x{:08X})",
+        image_path, size, crc
+    );
+
+    println!("发送固件信息 (REQ_SET_FW_INFO_1/2)...");
+    control_out_with_retry(
+        interface,
+        REQ_SET_FW_INFO_1,
+        (size & 0xFFFF) as u16,
+        ((size >> 16) & 0xFFFF) as u16,
+        &[],
+    )
+    .await?;
+    control_out_with_retry(
+        interface,
+        REQ_SET_FW_INFO_2,
+        (crc & 0xFFFF) as u16,
+        ((crc >> 16) & 0xFFFF) as u16,
+        &[],
+    )
+    .await?;
+
+    println!("擦除 Flash (REQ_SET_ERASE_FLASH)...");
+    let erase_req = ControlOut {
+        control_type: ControlType::Vendor,
+        recipient: Recipient::Interface,
+        request: REQ_SET_ERASE_FLASH,
+        value: 0,
+        index: 0,
+        data: &[],
+    };
+    interface.control_out(erase_req, ERASE_TIMEOUT).wait()?;
+
+    let total_blocks = image.chunks(FLASH_CHUNK_SIZE).count();
+    if total_blocks > u16::MAX as usize {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!(
+                "固件镜像过大: {} 块超过了 REQ_SET_UPDATE_DATA 的 wValue 块号上限 {}",
+                total_blocks,
+                u16::MAX
+            ),
+        ));
+    }
+
+    for (block_index, chunk) in image.chunks(FLASH_CHUNK_SIZE).enumerate() {
+        control_out_with_retry(interface, REQ_SET_UPDATE_DATA, block_index as u16, 0, chunk)
+            .await?;
+        print!(
+            "\r烧录进度: {}/{} 块 ({} 字节)",
+            block_index + 1,
+            total_blocks,
+            ((block_index + 1) * FLASH_CHUNK_SIZE).min(image.len()),
+        );
+        let _ = std::io::Write::flush(&mut std::io::stdout());
+    }
+    println!();
+
+    println!("跳转至 Bootloader (REQ_SET_FW_TO_BLDR)...");
+    control_out_with_retry(interface, REQ_SET_FW_TO_BLDR, 0, 0, &[]).await?;
+
+    println!("固件升级完成。");
+    Ok(())
+}
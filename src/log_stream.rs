@@ -0,0 +1,86 @@
+// --- 调试日志流式读取子系统 ---
+// 优先通过 interrupt-IN / bulk-IN 端点持续拉取调试日志：控制传输本该用于小型配置交换，
+// 而连续的设备->主机数据流按惯例应该走 bulk/interrupt 端点。相比每 10ms 轮询一次 8 字节
+// 控制传输，这里维持一个持续读取的缓冲流，避免两次轮询之间的数据丢失。
+// 仅在 --stream 模式下使用；该模式下接口没有合适的端点时，回退到 main.rs 里原有的
+// 控制轮询路径（未指定 --stream 时始终走控制轮询）。
+//
+// nusb 0.2.x 把端点操作做成了 typestate：`Interface::endpoint::<Kind, Dir>(addr)`
+// 按传输类型/方向在编译期换取一个具体类型的 `Endpoint`，描述符阶段的
+// bulk/interrupt 判别则走 `nusb::descriptors::TransferType` 这个普通枚举
+// （和用作泛型标记的 `Bulk`/`Interrupt` 标记类型是两回事），因此这里按运行时
+// 判别出的类型分别走 `Bulk`/`Interrupt` 两条分支来换取 typed endpoint。
+
+use std::io;
+
+use nusb::descriptors::TransferType;
+use nusb::transfer::{Bulk, Direction, In, Interrupt};
+
+// 每次读取的缓冲区大小
+const STREAM_BUFFER_SIZE: usize = 64;
+
+// 在接口当前设置中找到的 interrupt-IN / bulk-IN 端点
+pub enum LogEndpoint {
+    Bulk(u8),
+    Interrupt(u8),
+}
+
+// 在接口的当前设置中寻找第一个 interrupt-IN 或 bulk-IN 端点
+pub fn find_log_endpoint(interface: &nusb::Interface) -> Option<LogEndpoint> {
+    let descriptor = interface.descriptor()?;
+    descriptor.endpoints().find_map(|ep| {
+        if ep.direction() != Direction::In {
+            return None;
+        }
+        match ep.transfer_type() {
+            TransferType::Interrupt => Some(LogEndpoint::Interrupt(ep.address())),
+            TransferType::Bulk => Some(LogEndpoint::Bulk(ep.address())),
+            _ => None,
+        }
+    })
+}
+
+// 打印读取到的原始字节：截到第一个 0 字节（字符串结束符），和原有的控制轮询路径一致
+fn print_log_bytes(buf: &[u8]) {
+    let valid_len = buf.iter().position(|&x| x == 0).unwrap_or(buf.len());
+    let valid_bytes = &buf[0..valid_len];
+    if !valid_bytes.is_empty() {
+        let text = String::from_utf8_lossy(valid_bytes);
+        print!("{}", text);
+        let _ = std::io::Write::flush(&mut std::io::stdout());
+    }
+}
+
+// 持续从给定端点读取调试日志并打印，直到发生 I/O 错误或端点关闭（通常都意味着设备被拔出）
+pub async fn stream_debug_log(interface: &nusb::Interface, endpoint: LogEndpoint) -> io::Result<()> {
+    match endpoint {
+        LogEndpoint::Bulk(addr) => {
+            let ep = interface
+                .endpoint::<Bulk, In>(addr)
+                .map_err(|e| io::Error::new(io::ErrorKind::Other, e.to_string()))?;
+            let mut reader = ep.reader(STREAM_BUFFER_SIZE);
+            let mut buf = [0u8; STREAM_BUFFER_SIZE];
+            loop {
+                let n = reader.read(&mut buf).await?;
+                if n == 0 {
+                    return Err(io::Error::new(io::ErrorKind::UnexpectedEof, "端点已关闭"));
+                }
+                print_log_bytes(&buf[..n]);
+            }
+        }
+        LogEndpoint::Interrupt(addr) => {
+            let ep = interface
+                .endpoint::<Interrupt, In>(addr)
+                .map_err(|e| io::Error::new(io::ErrorKind::Other, e.to_string()))?;
+            let mut reader = ep.reader(STREAM_BUFFER_SIZE);
+            let mut buf = [0u8; STREAM_BUFFER_SIZE];
+            loop {
+                let n = reader.read(&mut buf).await?;
+                if n == 0 {
+                    return Err(io::Error::new(io::ErrorKind::UnexpectedEof, "端点已关闭"));
+                }
+                print_log_bytes(&buf[..n]);
+            }
+        }
+    }
+}
@@ -5,10 +5,10 @@ use nusb::{
     transfer::{ControlIn, ControlOut, ControlType, Recipient},
 };
 
-
-// --- 配置区域 ---
-const TARGET_VID: u16 = 0x343c; // 请替换为你的设备 VID
-const TARGET_PID: u16 = 0x5361; // 请替换为你的设备 PID
+mod descriptors;
+mod firmware;
+mod log_stream;
+mod reg;
 
 // --- 厂商请求定义 (对应 C 代码宏) ---
 // IN Requests
@@ -16,15 +16,20 @@ const REQ_GET_HARDWARE_STATUS: u8 = 0x01;
 const REQ_GET_FIRMWARE_STATUS: u8 = 0x02;
 const REQ_GET_FIRMWARE_VERSION: u8 = 0x03;
 const REQ_GET_DBG_MSG: u8 = 0x10;       // <--- 本次目标
-const REQ_GET_WR_REG: u8 = 0x11;
-const REQ_GET_RD_REG: u8 = 0x12;
-
-// OUT Requests (虽然这次不用，但先定义好)
-const REQ_SET_ERASE_FLASH: u8 = 0x10;
-const REQ_SET_UPDATE_DATA: u8 = 0x11;
-const REQ_SET_FW_INFO_1: u8 = 0x12;
-const REQ_SET_FW_INFO_2: u8 = 0x13;
-const REQ_SET_FW_TO_BLDR: u8 = 0x20;
+pub(crate) const REQ_GET_WR_REG: u8 = 0x11;
+pub(crate) const REQ_GET_RD_REG: u8 = 0x12;
+
+// OUT Requests
+pub(crate) const REQ_SET_ERASE_FLASH: u8 = 0x10;
+pub(crate) const REQ_SET_UPDATE_DATA: u8 = 0x11;
+pub(crate) const REQ_SET_FW_INFO_1: u8 = 0x12;
+pub(crate) const REQ_SET_FW_INFO_2: u8 = 0x13;
+pub(crate) const REQ_SET_FW_TO_BLDR: u8 = 0x20;
+// 寄存器写入的 OUT 请求码。注意不能照搬 REQ_GET_WR_REG (0x11)：
+// 那个值已经被 REQ_SET_UPDATE_DATA 用作固件数据块的 OUT 请求，
+// 两者 bmRequestType/Recipient 完全相同，沿用会让设备分不清这是一次
+// 寄存器写入还是一块固件数据，因此这里使用独立的 OUT 命名空间取值。
+pub(crate) const REQ_SET_WR_REG: u8 = 0x21;
 const REQ_SET_DBG_MSG: u8 = 0x22;
 
 // --- USB 常量定义 ---
@@ -33,56 +38,226 @@ const DESC_TYPE_BOS: u16 = 0x0F;
 const DESC_TYPE_DEVICE_CAPABILITY: u8 = 0x10;
 const CAP_TYPE_BILLBOARD: u8 = 0x0D;
 
-const REQ_GET_DESCRIPTOR: u8 = 0x06;
+pub(crate) const REQ_GET_DESCRIPTOR: u8 = 0x06;
+
+// 设备断开后，在重新拉起会话前等待的轮询间隔
+const DEVICE_WAIT_POLL_INTERVAL: Duration = Duration::from_millis(500);
+// 控制轮询模式下，连续多少次通信失败视为设备已断开（而不是一次偶发错误）
+const MAX_CONSECUTIVE_POLL_ERRORS: u32 = 5;
+
+// --- 命令行解析 ---
+// 代替之前硬编码的 TARGET_VID/TARGET_PID：
+//   usb-billboard list                                列出所有已连接的 USB 设备
+//   usb-billboard dump --vid 0x343c --pid 0x5361      打印完整的描述符树 (lsusb 风格) 后退出
+//   usb-billboard monitor --vid 0x343c --pid 0x5361 [--stream]   持续监听调试日志 (默认子命令)
+//   usb-billboard flash --vid 0x343c --pid 0x5361 --image fw.bin 烧录固件镜像并跳转 Bootloader
+enum Command {
+    List,
+    Dump { vid: u16, pid: u16 },
+    Monitor { vid: u16, pid: u16, use_stream: bool },
+    Flash { vid: u16, pid: u16, image_path: String },
+}
+
+fn parse_u16(s: &str) -> Result<u16, std::num::ParseIntError> {
+    if let Some(hex) = s.strip_prefix("0x").or_else(|| s.strip_prefix("0X")) {
+        u16::from_str_radix(hex, 16)
+    } else {
+        s.parse::<u16>()
+    }
+}
+
+fn print_usage() {
+    eprintln!("用法:");
+    eprintln!("  usb-billboard list");
+    eprintln!("  usb-billboard dump --vid <hex> --pid <hex>");
+    eprintln!("  usb-billboard [monitor] --vid <hex> --pid <hex> [--stream]");
+    eprintln!("  usb-billboard flash --vid <hex> --pid <hex> --image <固件文件路径>");
+}
+
+fn parse_args() -> Option<Command> {
+    let mut args: Vec<String> = std::env::args().skip(1).collect();
+
+    let subcommand = if !args.is_empty() && !args[0].starts_with("--") {
+        args.remove(0)
+    } else {
+        "monitor".to_string()
+    };
+
+    if subcommand == "list" {
+        return Some(Command::List);
+    }
+
+    let mut vid = None;
+    let mut pid = None;
+    let mut use_stream = false;
+    let mut image_path = None;
+    let mut iter = args.into_iter();
+    while let Some(arg) = iter.next() {
+        match arg.as_str() {
+            "--vid" => vid = iter.next().and_then(|v| parse_u16(&v).ok()),
+            "--pid" => pid = iter.next().and_then(|v| parse_u16(&v).ok()),
+            "--stream" => use_stream = true,
+            "--image" => image_path = iter.next(),
+            other => {
+                eprintln!("未知参数: {}", other);
+                return None;
+            }
+        }
+    }
+
+    let (vid, pid) = match (vid, pid) {
+        (Some(vid), Some(pid)) => (vid, pid),
+        _ => {
+            eprintln!("错误: 必须通过 --vid 和 --pid 指定目标设备。");
+            return None;
+        }
+    };
+
+    match subcommand.as_str() {
+        "dump" => Some(Command::Dump { vid, pid }),
+        "monitor" => Some(Command::Monitor { vid, pid, use_stream }),
+        "flash" => match image_path {
+            Some(image_path) => Some(Command::Flash { vid, pid, image_path }),
+            None => {
+                eprintln!("错误: flash 子命令必须通过 --image 指定固件文件路径。");
+                None
+            }
+        },
+        other => {
+            eprintln!("未知子命令: {}", other);
+            None
+        }
+    }
+}
 
 #[tokio::main]
 async fn main() {
-    println!(
-        "正在查找设备 VID:0x{:04X} PID:0x{:04X}...",
-        TARGET_VID, TARGET_PID
-    );
-
-    // 1. 查找设备
-    // nusb 0.2: list_devices() 返回 Result<Iterator>
-    let device_info = match nusb::list_devices()
-        .wait()
-        .unwrap()
-        .find(|d| d.vendor_id() == TARGET_VID && d.product_id() == TARGET_PID)
-    {
-        Some(d) => d,
+    let command = match parse_args() {
+        Some(c) => c,
         None => {
-            eprintln!("错误: 未找到设备。");
-            eprintln!("提示: 请检查连接，并确保已使用 Zadig 安装 WinUSB 驱动。");
-            return;
+            print_usage();
+            std::process::exit(1);
         }
     };
 
-    println!(
-        "找到设备: {}",
-        device_info.product_string().unwrap_or("未知设备")
-    );
+    match command {
+        Command::List => {
+            if let Err(e) = descriptors::list_all_devices() {
+                eprintln!("枚举设备失败: {}", e);
+            }
+        }
+        Command::Dump { vid, pid } => {
+            println!("正在查找设备 VID:0x{:04X} PID:0x{:04X}...", vid, pid);
+            match nusb::list_devices()
+                .wait()
+                .ok()
+                .and_then(|mut devices| devices.find(|d| d.vendor_id() == vid && d.product_id() == pid))
+            {
+                Some(device_info) => {
+                    if let Err(e) = run_dump_session(device_info).await {
+                        eprintln!("Dump 会话失败: {}", e);
+                    }
+                }
+                None => eprintln!("错误: 未找到设备。"),
+            }
+        }
+        Command::Flash { vid, pid, image_path } => {
+            println!("正在查找设备 VID:0x{:04X} PID:0x{:04X}...", vid, pid);
+            match nusb::list_devices()
+                .wait()
+                .ok()
+                .and_then(|mut devices| devices.find(|d| d.vendor_id() == vid && d.product_id() == pid))
+            {
+                Some(device_info) => {
+                    if let Err(e) = run_flash_session(device_info, &image_path).await {
+                        eprintln!("固件升级失败: {}", e);
+                    }
+                }
+                None => eprintln!("错误: 未找到设备。"),
+            }
+        }
+        Command::Monitor { vid, pid, use_stream } => {
+            println!("正在查找设备 VID:0x{:04X} PID:0x{:04X}...", vid, pid);
 
-    // 2. 打开设备
-    let device = match device_info.open().wait() {
-        Ok(d) => d,
-        Err(e) => {
-            eprintln!("无法打开设备: {}", e);
-            eprintln!("常见原因: 驱动被系统占用或非 WinUSB 驱动。");
-            return;
+            // 上一次会话里的寄存器控制台任务句柄：每次重连前先 abort() 掉它，
+            // 避免多个控制台任务同时争抢同一个 stdin（其中旧的还绑定着已断开的 Interface）
+            let mut console_handle: Option<tokio::task::JoinHandle<()>> = None;
+
+            // 热插拔监督循环：等待设备出现 -> 跑一次完整会话 -> 会话因断开结束后回到等待
+            loop {
+                let device_info = wait_for_device(vid, pid).await;
+                println!(
+                    "找到设备: {}",
+                    device_info.product_string().unwrap_or("未知设备")
+                );
+
+                if let Err(e) = run_session(device_info, use_stream, &mut console_handle).await {
+                    eprintln!("设备会话结束 ({})，等待设备重新插入...", e);
+                }
+            }
         }
-    };
+    }
+}
+
+// `dump` 子命令：打开设备、解析 BOS/Billboard 小节，再打印完整的描述符树，然后退出
+async fn run_dump_session(device_info: nusb::DeviceInfo) -> std::io::Result<()> {
+    let device = device_info.open().wait()?;
+    let interface = device.claim_interface(0).await?;
+
+    match get_bos_descriptor(&interface).await {
+        Ok(data) => {
+            println!("BOS 描述符读取成功 ({} bytes)，开始解析...", data.len());
+            parse_bos_data(&interface, &data).await;
+        }
+        Err(e) => eprintln!("读取 BOS 描述符失败: {}", e),
+    }
+
+    descriptors::dump_descriptor_tree(&interface).await;
+    Ok(())
+}
+
+// `flash` 子命令：打开设备、认领接口，然后驱动完整的固件升级流程
+async fn run_flash_session(device_info: nusb::DeviceInfo, image_path: &str) -> std::io::Result<()> {
+    let device = device_info.open().wait()?;
+    let interface = device.claim_interface(0).await?;
+    firmware::flash_firmware(&interface, image_path).await
+}
+
+// 轮询 list_devices，直到匹配的 VID/PID 出现（nusb 目前未提供跨平台的
+// 事件式热插拔 API，因此这里采用文档允许的轮询回退方案）
+async fn wait_for_device(vid: u16, pid: u16) -> nusb::DeviceInfo {
+    loop {
+        if let Ok(mut devices) = nusb::list_devices().wait() {
+            if let Some(d) = devices.find(|d| d.vendor_id() == vid && d.product_id() == pid) {
+                return d;
+            }
+        }
+        tokio::time::sleep(DEVICE_WAIT_POLL_INTERVAL).await;
+    }
+}
+
+// 针对单次设备插入运行的完整会话：打开、认领接口、解析 BOS、初始化、
+// 然后持续读取调试日志，直到遇到通信错误（视为设备被拔出）后返回。
+// `console_handle` 跨重连持久化，用来在启动新的寄存器控制台前，终止绑定着
+// 上一个（已断开的）Interface 的旧控制台任务。
+async fn run_session(
+    device_info: nusb::DeviceInfo,
+    use_stream: bool,
+    console_handle: &mut Option<tokio::task::JoinHandle<()>>,
+) -> std::io::Result<()> {
+    // 2. 打开设备
+    let device = device_info.open().wait().map_err(|e| {
+        eprintln!("常见原因: 驱动被系统占用或非 WinUSB 驱动。");
+        e
+    })?;
 
     // Windows/WinUSB 必须先认领一个接口才能发送控制传输
     // 通常我们认领接口 0 即可
     println!("正在认领接口 0 以初始化 WinUSB...");
-    let interface = match device.claim_interface(0).await {
-        Ok(i) => i,
-        Err(e) => {
-            eprintln!("认领接口失败: {}", e);
-            eprintln!("提示: 即使是读取设备级描述符，WinUSB 也需要认领一个接口。");
-            return;
-        }
-    };
+    let interface = device.claim_interface(0).await.map_err(|e| {
+        eprintln!("提示: 即使是读取设备级描述符，WinUSB 也需要认领一个接口。");
+        e
+    })?;
 
     // 使用 interface 句柄读取设备级的 BOS 描述符
     match get_bos_descriptor(&interface).await {
@@ -103,11 +278,54 @@ async fn main() {
         println!("初始化成功，开始监听日志...");
     }
 
-    // 4. 循环读取调试信息 (GET_DBG_MSG)
-    println!("\n--- 开始打印调试日志 按Ctrl-C退出 ---");
+    // 终止上一次会话遗留的控制台任务（如果有），再启动绑定当前 Interface 的新任务，
+    // 与调试日志并行运行
+    if let Some(old_handle) = console_handle.take() {
+        old_handle.abort();
+    }
+    *console_handle = Some(reg::spawn_console(interface.clone()));
+
+    // 4. 读取调试信息：优先使用 --stream 模式下的 interrupt/bulk IN 端点流，
+    //    只有在没有合适端点，或未指定 --stream 时，才回退到控制传输轮询
+    let stream_endpoint = if use_stream {
+        log_stream::find_log_endpoint(&interface)
+    } else {
+        None
+    };
+
+    println!("\n--- 开始打印调试日志 (设备拔出后会自动等待重连) ---");
+    match stream_endpoint {
+        Some(endpoint) => {
+            match &endpoint {
+                log_stream::LogEndpoint::Interrupt(addr) => {
+                    println!("检测到 interrupt-IN 端点 0x{:02X}，使用流式模式读取调试日志", addr)
+                }
+                log_stream::LogEndpoint::Bulk(addr) => {
+                    println!("检测到 bulk-IN 端点 0x{:02X}，使用流式模式读取调试日志", addr)
+                }
+            }
+            log_stream::stream_debug_log(&interface, endpoint).await
+        }
+        None => {
+            if use_stream {
+                println!("未找到合适的 interrupt/bulk IN 端点，回退到控制传输轮询模式");
+            }
+            poll_dbg_msg_loop(&interface).await
+        }
+    }
+
+    // 本地的 `interface`/`device` 在此处离开作用域并被丢弃，下一轮会为重新插入的设备
+    // 重新创建它们；控制台任务持有的 Interface 克隆要到下一次会话 abort() 它时才释放
+}
+
+// 控制传输轮询模式：每 10ms 发起一次 8 字节的 REQ_GET_DBG_MSG 控制读取。
+// 连续失败达到阈值时视为设备已断开，返回错误交由上层监督循环重新等待设备。
+async fn poll_dbg_msg_loop(interface: &nusb::Interface) -> std::io::Result<()> {
+    let mut consecutive_errors = 0u32;
     loop {
-        match get_dbg_msg(&interface).await {
+        match get_dbg_msg(interface).await {
             Ok(data) => {
+                consecutive_errors = 0;
                 let valid_len = data.iter().position(|&x| x == 0).unwrap_or(data.len());
                 // 获取有效切片 (Slice)
                 let valid_bytes = &data[0..valid_len];
@@ -120,7 +338,11 @@ async fn main() {
             },
             Err(e) => {
                 // 只有真正的 USB 通信错误才报错，而不是数据内容错误
-                eprintln!("通信读取出错: {}", e);
+                consecutive_errors += 1;
+                eprintln!("通信读取出错 ({}/{}): {}", consecutive_errors, MAX_CONSECUTIVE_POLL_ERRORS, e);
+                if consecutive_errors >= MAX_CONSECUTIVE_POLL_ERRORS {
+                    return Err(e);
+                }
                 tokio::time::sleep(Duration::from_secs(1)).await;
             }
         }
@@ -141,7 +363,7 @@ async fn set_dbg_msg(interface: &nusb::Interface) -> std::io::Result<()> {
     };
 
     // 发送请求，忽略返回值(写入字节数)
-    interface.control_out(req, Duration::from_millis(200)).wait().unwrap();
+    interface.control_out(req, Duration::from_millis(200)).wait()?;
     Ok(())
 }
 
@@ -159,7 +381,7 @@ async fn get_dbg_msg(interface: &nusb::Interface) -> std::io::Result<Vec<u8>> {
     };
 
     // 发送请求
-    let data = interface.control_in(req, Duration::from_millis(200)).wait().unwrap();
+    let data = interface.control_in(req, Duration::from_millis(200)).wait()?;
 
     // 检查长度（可选）
     if data.len() != 8 {
@@ -183,8 +405,7 @@ async fn get_bos_descriptor(interface: &nusb::Interface) -> std::io::Result<Vec<
 
     let header = interface
         .control_in(header_req, Duration::from_millis(200))
-        .wait()
-        .unwrap();
+        .wait()?;
 
     if header.len() < 5 {
         return Err(std::io::Error::new(
@@ -209,8 +430,7 @@ async fn get_bos_descriptor(interface: &nusb::Interface) -> std::io::Result<Vec<
 
     let data = interface
         .control_in(full_req, Duration::from_millis(200))
-        .wait()
-        .unwrap();
+        .wait()?;
     Ok(data)
 }
 
@@ -249,19 +469,52 @@ async fn parse_bos_data(interface: &nusb::Interface, data: &[u8]) {
     }
 }
 
+// 将 bmConfigured 中的 2-bit 状态码转换为可读文本
+fn configured_state_str(state: u8) -> &'static str {
+    match state {
+        0 => "未指定 (Unspecified)",
+        1 => "未尝试 (Not attempted)",
+        2 => "尝试失败 (Unsuccessful)",
+        3 => "已配置/成功 (Configured)",
+        _ => unreachable!("2-bit 状态码只有 4 种取值"),
+    }
+}
+
+// 从 bmConfigured 位图中取出第 mode 个 alt mode 的 2-bit 状态。
+// bm_configured 固定为 32 字节 (每字节 4 个 alt mode)，只能容纳 128 个 alt mode，
+// 而 mode 来自不可信的 bNumberOfAlternateModes (u8，最大 255)，超出范围时返回 None
+// 而不是索引越界 panic。
+fn alt_mode_configured_state(bm_configured: &[u8], mode: usize) -> Option<u8> {
+    if mode / 4 >= bm_configured.len() {
+        return None;
+    }
+    let byte = bm_configured[mode / 4];
+    let shift = (mode % 4) * 2;
+    Some((byte >> shift) & 0x03)
+}
+
 // 解析 Billboard 具体字段
 async fn process_billboard_cap(interface: &nusb::Interface, buf: &[u8]) {
-    if buf.len() < 40 {
-        println!("警告: Billboard 描述符长度不足 (标准至少40字节)");
+    if buf.len() < 44 {
+        println!("警告: Billboard 描述符长度不足 (标准至少44字节，包含定长部分)");
+        return;
     }
 
     let url_index = buf[3];
     let num_alt_modes = buf[4];
     let preferred_mode = buf[5];
+    let vconn_power = u16::from_le_bytes([buf[6], buf[7]]);
+    let bm_configured = &buf[8..40];
+    let bcd_version = u16::from_le_bytes([buf[40], buf[41]]);
+    let additional_failure_info = buf[42];
+    // buf[43] 是 bAdditionalFailureInfo 之后保留的 bReserved
 
     println!("  -> Alternate Modes 数量: {}", num_alt_modes);
     println!("  -> 首选模式索引: {}", preferred_mode);
     println!("  -> URL 字符串索引: {}", url_index);
+    println!("  -> VCONN Power: 0x{:04X}", vconn_power);
+    println!("  -> bcdVersion: {:#06X}", bcd_version);
+    println!("  -> bAdditionalFailureInfo: {}", additional_failure_info);
 
     if url_index > 0 {
         print!("  -> 读取 URL: ");
@@ -270,10 +523,45 @@ async fn process_billboard_cap(interface: &nusb::Interface, buf: &[u8]) {
             Err(_) => println!("[读取失败]"),
         }
     }
+
+    // 紧跟在定长部分之后的是 num_alt_modes 个 4 字节的 Alternate Mode 记录
+    let records_start = 44;
+    for mode in 0..num_alt_modes as usize {
+        let record_offset = records_start + mode * 4;
+        if record_offset + 4 > buf.len() {
+            println!("  警告: Alternate Mode 记录 #{} 超出描述符范围", mode);
+            break;
+        }
+
+        let record = &buf[record_offset..record_offset + 4];
+        let svid = u16::from_le_bytes([record[0], record[1]]);
+        let alt_mode = record[2];
+        let alt_mode_string_index = record[3];
+        let state = match alt_mode_configured_state(bm_configured, mode) {
+            Some(state) => configured_state_str(state),
+            None => {
+                println!("  警告: Alt Mode #{} 超出 bmConfigured 位图范围", mode);
+                "未知 (超出范围)"
+            }
+        };
+
+        print!(
+            "  -> Alt Mode #{}: SVID=0x{:04X} Mode={} 状态={}",
+            mode, svid, alt_mode, state
+        );
+        if alt_mode_string_index > 0 {
+            match get_string_descriptor(interface, alt_mode_string_index).await {
+                Ok(s) => println!(" 名称={}", s),
+                Err(_) => println!(" 名称=[读取失败]"),
+            }
+        } else {
+            println!();
+        }
+    }
 }
 
 // 辅助函数：读取字符串描述符
-async fn get_string_descriptor(interface: &nusb::Interface, index: u8) -> std::io::Result<String> {
+pub(crate) async fn get_string_descriptor(interface: &nusb::Interface, index: u8) -> std::io::Result<String> {
     let lang_id = 0x0409;
 
     let data = interface
@@ -288,8 +576,7 @@ async fn get_string_descriptor(interface: &nusb::Interface, index: u8) -> std::i
             },
             Duration::from_millis(200),
         )
-        .wait()
-        .unwrap();
+        .wait()?;
 
     if data.len() < 2 {
         return Ok("".to_string());
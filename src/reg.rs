@@ -0,0 +1,95 @@
+// --- 寄存器读写子系统 ---
+// 基于 REQ_GET_RD_REG (IN) / REQ_SET_WR_REG (OUT) 的寄存器 peek/poke 辅助函数，
+// 以及一个运行在独立任务中的交互式命令行 (`read 0x40` / `write 0x40 0x1234`)。
+// 注意：写入没有使用 REQ_GET_WR_REG 的值 (0x11)，因为那个值在 OUT 方向上
+// 已经被 firmware 模块的 REQ_SET_UPDATE_DATA 占用，参见 REQ_SET_WR_REG 的注释。
+
+use std::io;
+use std::time::Duration;
+
+use nusb::transfer::{ControlIn, ControlOut, ControlType, Recipient};
+use nusb::MaybeFuture;
+use tokio::io::{AsyncBufReadExt, BufReader};
+
+use crate::{REQ_GET_RD_REG, REQ_SET_WR_REG};
+
+const CONTROL_TIMEOUT: Duration = Duration::from_millis(200);
+
+// 读取 32 位寄存器，寄存器地址编码在 wIndex 中
+pub async fn read_reg(interface: &nusb::Interface, addr: u16) -> io::Result<u32> {
+    let req = ControlIn {
+        control_type: ControlType::Vendor,
+        recipient: Recipient::Interface,
+        request: REQ_GET_RD_REG,
+        value: 0,
+        index: addr,
+        length: 4,
+    };
+
+    let data = interface.control_in(req, CONTROL_TIMEOUT).wait()?;
+    if data.len() < 4 {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!("读寄存器返回数据过短: 预期 4 字节，实际 {} 字节", data.len()),
+        ));
+    }
+
+    Ok(u32::from_le_bytes([data[0], data[1], data[2], data[3]]))
+}
+
+// 写 32 位寄存器：地址编码在 wIndex，低 16 位额外放在 wValue 中，完整值放在数据阶段
+pub async fn write_reg(interface: &nusb::Interface, addr: u16, val: u32) -> io::Result<()> {
+    let data = val.to_le_bytes();
+    let req = ControlOut {
+        control_type: ControlType::Vendor,
+        recipient: Recipient::Interface,
+        request: REQ_SET_WR_REG,
+        value: (val & 0xFFFF) as u16,
+        index: addr,
+        data: &data,
+    };
+
+    interface.control_out(req, CONTROL_TIMEOUT).wait()?;
+    Ok(())
+}
+
+// 解析形如 "0x40" / "40" 的十六进制或十进制数值
+fn parse_number(s: &str) -> Result<u32, std::num::ParseIntError> {
+    if let Some(hex) = s.strip_prefix("0x").or_else(|| s.strip_prefix("0X")) {
+        u32::from_str_radix(hex, 16)
+    } else {
+        s.parse::<u32>()
+    }
+}
+
+// 在后台任务中运行一个简单的 `read <addr>` / `write <addr> <val>` 交互控制台，
+// 与主循环中的调试日志打印并行工作。返回任务句柄，供调用方在设备重连时
+// `abort()` 掉绑定着旧 `Interface` 的上一个控制台任务，避免多个任务同时
+// 读取同一个全局 stdin。
+pub fn spawn_console(interface: nusb::Interface) -> tokio::task::JoinHandle<()> {
+    tokio::spawn(async move {
+        println!("寄存器控制台已启动 (用法: read 0x40 | write 0x40 0x1234)");
+        let mut lines = BufReader::new(tokio::io::stdin()).lines();
+        while let Ok(Some(line)) = lines.next_line().await {
+            let parts: Vec<&str> = line.trim().split_whitespace().collect();
+            match parts.as_slice() {
+                ["read", addr] => match parse_number(addr) {
+                    Ok(addr) => match read_reg(&interface, addr as u16).await {
+                        Ok(val) => println!("[寄存器] 0x{:04X} = 0x{:08X}", addr, val),
+                        Err(e) => eprintln!("[寄存器] 读取失败: {}", e),
+                    },
+                    Err(_) => eprintln!("[寄存器] 无效地址: {}", addr),
+                },
+                ["write", addr, val] => match (parse_number(addr), parse_number(val)) {
+                    (Ok(addr), Ok(val)) => match write_reg(&interface, addr as u16, val).await {
+                        Ok(()) => println!("[寄存器] 写入 0x{:04X} = 0x{:08X} 成功", addr, val),
+                        Err(e) => eprintln!("[寄存器] 写入失败: {}", e),
+                    },
+                    _ => eprintln!("[寄存器] 无效的地址或数值: {} {}", addr, val),
+                },
+                [] => {}
+                _ => eprintln!("[寄存器] 未知命令，用法: read 0x40 | write 0x40 0x1234"),
+            }
+        }
+    })
+}